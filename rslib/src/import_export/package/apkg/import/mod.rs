@@ -0,0 +1,73 @@
+// Copyright: Ankitects Pty Ltd and contributors
+// License: GNU AGPL, version 3 or later; http://www.gnu.org/licenses/agpl.html
+
+mod notes;
+
+use crate::import_export::package::media::MediaUseMap;
+use crate::import_export::ImportProgress;
+use crate::import_export::NoteLog;
+use crate::prelude::*;
+use crate::progress::ThrottlingProgressHandler;
+
+pub use notes::ImportConflictResolution;
+
+/// The notetypes and notes read from an `.apkg`/`.colpkg` archive, still
+/// keyed by their source-side ids.
+#[derive(Debug, Default)]
+pub(super) struct ImportData {
+    pub(super) notetypes: Vec<Notetype>,
+    pub(super) notes: Vec<Note>,
+}
+
+/// Mutable state shared across the phases of importing an archive's notes
+/// and notetypes into `target_col`.
+pub(super) struct Context<'a> {
+    target_col: &'a mut Collection,
+    usn: Usn,
+    data: ImportData,
+    progress: ThrottlingProgressHandler<ImportProgress>,
+    conflict_resolution: ImportConflictResolution,
+    possible_duplicate_threshold: Option<f32>,
+}
+
+impl<'a> Context<'a> {
+    fn new(
+        target_col: &'a mut Collection,
+        usn: Usn,
+        data: ImportData,
+        conflict_resolution: ImportConflictResolution,
+        possible_duplicate_threshold: Option<f32>,
+    ) -> Self {
+        let progress = target_col.new_progress_handler();
+        Self {
+            target_col,
+            usn,
+            data,
+            progress,
+            conflict_resolution,
+            possible_duplicate_threshold,
+        }
+    }
+}
+
+/// Import the notetypes and notes of an archive into `target_col`, resolving
+/// GUID conflicts according to `conflict_resolution` and optionally flagging
+/// likely duplicates whose combined similarity score is at least
+/// `possible_duplicate_threshold`.
+pub(super) fn import_notes_and_notetypes(
+    target_col: &mut Collection,
+    usn: Usn,
+    data: ImportData,
+    media_map: &mut MediaUseMap,
+    conflict_resolution: ImportConflictResolution,
+    possible_duplicate_threshold: Option<f32>,
+) -> Result<NoteLog> {
+    let mut ctx = Context::new(
+        target_col,
+        usn,
+        data,
+        conflict_resolution,
+        possible_duplicate_threshold,
+    );
+    Ok(ctx.import_notes_and_notetypes(media_map)?.log)
+}
@@ -23,13 +23,46 @@ struct NoteContext<'a> {
     target_col: &'a mut Collection,
     usn: Usn,
     normalize_notes: bool,
+    conflict_resolution: ImportConflictResolution,
     remapped_notetypes: HashMap<NotetypeId, NotetypeId>,
     target_guids: HashMap<String, NoteMeta>,
     target_ids: HashSet<NoteId>,
+    /// Target notes keyed by a content fingerprint, used to detect notes that
+    /// were re-exported with a regenerated GUID instead of actually being
+    /// new. More than one target note sharing a fingerprint is tracked so we
+    /// know to skip the optimization rather than guess.
+    target_fingerprints: HashMap<Sha1Hash, Vec<NoteId>>,
+    /// Inverted term index over target notes, for surfacing likely (but not
+    /// certain) duplicates that a GUID/fingerprint match would miss. `None`
+    /// unless the caller opted in, as it costs memory proportional to the
+    /// size of the target collection.
+    possible_duplicates: Option<PossibleDuplicateIndex>,
+    /// How to reorder the fields of an incoming note using a notetype that
+    /// was structurally merged into an existing one, keyed by that
+    /// notetype's (unchanged) id.
+    field_remaps: HashMap<NotetypeId, FieldRemap>,
     media_map: &'a mut MediaUseMap,
     imports: NoteImports,
 }
 
+/// How to handle a note whose GUID already exists in the target collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportConflictResolution {
+    /// Keep whichever side was modified most recently. This is the
+    /// traditional behaviour of apkg/backup imports.
+    #[default]
+    PreferNewest,
+    /// Always take the incoming note, discarding any local changes.
+    PreferSource,
+    /// Always keep the target note, discarding the incoming one.
+    PreferTarget,
+    /// Three-way merge the two notes field by field, using a snapshot of the
+    /// last import of this GUID as the common ancestor. If no such snapshot
+    /// exists yet, the note is logged as a duplicate when its fields already
+    /// match the target's, and as conflicting otherwise.
+    Merge,
+}
+
 #[derive(Debug, Default)]
 pub(super) struct NoteImports {
     pub(super) id_map: HashMap<NoteId, NoteId>,
@@ -49,6 +82,26 @@ impl NoteImports {
         self.log.updated.push(note.into_log_note());
     }
 
+    fn log_merged(&mut self, note: Note, source_id: NoteId) {
+        self.id_map.insert(source_id, note.id);
+        self.log.merged.push(note.into_log_note());
+    }
+
+    fn log_renamed(&mut self, note: Note, source_id: NoteId) {
+        self.id_map.insert(source_id, note.id);
+        self.log.renamed.push(note.into_log_note());
+    }
+
+    /// Flag `note` as a likely (but not certain) duplicate of `target_id`,
+    /// without suppressing its import.
+    fn log_possible_duplicate(&mut self, note: &Note, target_id: NoteId) {
+        self.log.possible_duplicates.push(PossibleDuplicate {
+            note_id: note.id,
+            fields: note.fields().to_vec(),
+            target_id,
+        });
+    }
+
     fn log_duplicate(&mut self, mut note: Note, target_id: NoteId) {
         self.id_map.insert(note.id, target_id);
         // id is for looking up note in *target* collection
@@ -78,12 +131,28 @@ impl NoteMeta {
     }
 }
 
+/// A note imported as new that closely resembles an existing target note
+/// under a different GUID, surfaced for the user to review rather than
+/// silently imported as-is or suppressed.
+#[derive(Debug, Clone)]
+pub(crate) struct PossibleDuplicate {
+    pub(crate) note_id: NoteId,
+    pub(crate) fields: Vec<String>,
+    pub(crate) target_id: NoteId,
+}
+
 impl Context<'_> {
     pub(super) fn import_notes_and_notetypes(
         &mut self,
         media_map: &mut MediaUseMap,
     ) -> Result<NoteImports> {
-        let mut ctx = NoteContext::new(self.usn, self.target_col, media_map)?;
+        let mut ctx = NoteContext::new(
+            self.usn,
+            self.target_col,
+            media_map,
+            self.conflict_resolution,
+            self.possible_duplicate_threshold,
+        )?;
         ctx.import_notetypes(mem::take(&mut self.data.notetypes))?;
         ctx.import_notes(mem::take(&mut self.data.notes), &mut self.progress)?;
         Ok(ctx.imports)
@@ -95,17 +164,33 @@ impl<'n> NoteContext<'n> {
         usn: Usn,
         target_col: &'a mut Collection,
         media_map: &'a mut MediaUseMap,
+        conflict_resolution: ImportConflictResolution,
+        possible_duplicate_threshold: Option<f32>,
     ) -> Result<Self> {
         let target_guids = target_col.storage.note_guid_map()?;
         let normalize_notes = target_col.get_config_bool(BoolKey::NormalizeNoteText);
         let target_ids = target_col.storage.get_all_note_ids()?;
+        let target_notes = target_col.storage.get_all_notes()?;
+        let mut target_fingerprints: HashMap<Sha1Hash, Vec<NoteId>> = HashMap::new();
+        for note in &target_notes {
+            target_fingerprints
+                .entry(note_fingerprint(note))
+                .or_default()
+                .push(note.id);
+        }
+        let possible_duplicates = possible_duplicate_threshold
+            .map(|threshold| PossibleDuplicateIndex::build(&target_notes, threshold));
         Ok(Self {
             target_col,
             usn,
             normalize_notes,
+            conflict_resolution,
             remapped_notetypes: HashMap::new(),
             target_guids,
             target_ids,
+            target_fingerprints,
+            possible_duplicates,
+            field_remaps: HashMap::new(),
             imports: NoteImports::default(),
             media_map,
         })
@@ -131,12 +216,54 @@ impl<'n> NoteContext<'n> {
             if incoming.mtime_secs > existing.mtime_secs {
                 self.update_notetype(incoming, existing)?;
             }
+        } else if let Some(plan) = structural_addition_plan(incoming, &existing) {
+            // The incoming notetype only adds fields/templates on top of the
+            // existing ones; overwrite in place instead of forking a new
+            // notetype id for what's still fundamentally the same notetype.
+            self.apply_structural_addition(incoming, existing, plan)?;
         } else {
             self.add_notetype_with_remapped_id(incoming)?;
         }
         Ok(())
     }
 
+    /// Add any fields/templates `incoming` has that `existing` lacks onto
+    /// `existing`, keep `existing`'s id, and remember how to reorder the
+    /// fields of notes using `incoming`'s (now-stale) field ordinals.
+    ///
+    /// Notes already stored under `existing`'s id are resized to the new
+    /// field count by [Self::update_notetype]'s
+    /// `add_or_update_notetype_with_existing_id_inner` call, the same path
+    /// used for ordinary field-list edits made via the notetype manager.
+    fn apply_structural_addition(
+        &mut self,
+        incoming: &mut Notetype,
+        existing: Notetype,
+        plan: StructuralAdditionPlan,
+    ) -> Result<()> {
+        let mut merged = existing.clone();
+        let existing_field_names: HashSet<String> =
+            merged.fields.iter().map(|f| f.name.clone()).collect();
+        for field in &incoming.fields {
+            if !existing_field_names.contains(&field.name) {
+                merged.fields.push(field.clone());
+            }
+        }
+        let existing_template_names: HashSet<String> =
+            merged.templates.iter().map(|t| t.name.clone()).collect();
+        for template in &incoming.templates {
+            if !existing_template_names.contains(&template.name) {
+                merged.templates.push(template.clone());
+            }
+        }
+        merged.mtime_secs = incoming.mtime_secs;
+
+        let notetype_id = merged.id;
+        self.update_notetype(&mut merged, existing)?;
+        self.field_remaps.insert(notetype_id, plan.into_remap());
+        Ok(())
+    }
+
     fn add_notetype(&mut self, notetype: &mut Notetype) -> Result<()> {
         notetype.prepare_for_update(None, true)?;
         self.target_col
@@ -170,19 +297,20 @@ impl<'n> NoteContext<'n> {
         self.imports.log.found_notes = notes.len() as u32;
         for mut note in notes {
             incrementor.increment()?;
+            if let Some(remap) = self.field_remaps.get(&note.notetype_id) {
+                remap.apply(&mut note);
+            }
             let remapped_notetype_id = self.remapped_notetypes.get(&note.notetype_id);
-            if let Some(existing_note) = self.target_guids.get(&note.guid) {
-                if existing_note.mtime < note.mtime {
-                    if existing_note.notetype_id != note.notetype_id
-                        || remapped_notetype_id.is_some()
-                    {
-                        // Existing GUID with different notetype id, or changed notetype schema
-                        self.imports.log_conflicting(note);
-                    } else {
-                        self.update_note(note, existing_note.id)?;
-                    }
+            if let Some(existing_note) = self.target_guids.get(&note.guid).copied() {
+                if existing_note.notetype_id != note.notetype_id || remapped_notetype_id.is_some() {
+                    // Existing GUID with different notetype id, or changed notetype
+                    // schema. Always flagged as conflicting regardless of mtime or
+                    // conflict_resolution: PreferSource/PreferTarget/Merge all assume
+                    // both sides share a notetype, so there's no sound way to apply
+                    // them across a notetype change.
+                    self.imports.log_conflicting(note);
                 } else {
-                    self.imports.log_duplicate(note, existing_note.id);
+                    self.resolve_conflict(note, existing_note)?;
                 }
             } else {
                 if let Some(remapped_ntid) = remapped_notetype_id {
@@ -190,13 +318,66 @@ impl<'n> NoteContext<'n> {
                     // with a new notetype id.
                     note.notetype_id = *remapped_ntid;
                 }
-                self.add_note(note)?;
+                if let Some(target_id) = self.find_renamed_target(&note) {
+                    // Same content, new GUID: this is a re-export of a note we already
+                    // have, not a genuinely new one.
+                    self.update_renamed_note(note, target_id)?;
+                } else {
+                    if let Some(target_id) = self.find_possible_duplicate(&note) {
+                        self.imports.log_possible_duplicate(&note, target_id);
+                    }
+                    self.add_note(note)?;
+                }
             }
         }
 
         Ok(())
     }
 
+    /// Decide what to do with an incoming note whose GUID already exists in
+    /// the target collection and whose notetype matches, according to
+    /// [Self::conflict_resolution].
+    fn resolve_conflict(&mut self, note: Note, existing: NoteMeta) -> Result<()> {
+        match self.conflict_resolution {
+            ImportConflictResolution::PreferNewest => {
+                if existing.mtime < note.mtime {
+                    self.update_note(note, existing.id)
+                } else {
+                    self.imports.log_duplicate(note, existing.id);
+                    Ok(())
+                }
+            }
+            ImportConflictResolution::PreferSource => self.update_note(note, existing.id),
+            ImportConflictResolution::PreferTarget => {
+                self.imports.log_duplicate(note, existing.id);
+                Ok(())
+            }
+            ImportConflictResolution::Merge => {
+                if let Some(ancestor) = self
+                    .target_col
+                    .storage
+                    .get_note_field_snapshot(&note.guid)?
+                {
+                    self.merge_note(note, existing, ancestor)
+                } else {
+                    // No recorded ancestor to merge against. If the two sides already
+                    // agree there's nothing to lose by treating this as a duplicate,
+                    // but if they've actually diverged there's no sound way to tell
+                    // which side should win, so surface it instead of silently
+                    // picking one and discarding the other.
+                    let target = self.get_expected_note(existing.id)?;
+                    if target.fields() == note.fields() {
+                        self.imports.log_duplicate(note, existing.id);
+                        Ok(())
+                    } else {
+                        self.imports.log_conflicting(note);
+                        Ok(())
+                    }
+                }
+            }
+        }
+    }
+
     fn add_note(&mut self, mut note: Note) -> Result<()> {
         self.munge_media(&mut note)?;
         self.target_col.canonify_note_tags(&mut note, self.usn)?;
@@ -207,6 +388,7 @@ impl<'n> NoteContext<'n> {
 
         self.target_col.add_note_only_with_id_undoable(&mut note)?;
         self.target_ids.insert(note.id);
+        self.store_field_snapshot(&note)?;
         self.imports.log_new(note, old_id);
 
         Ok(())
@@ -243,10 +425,109 @@ impl<'n> NoteContext<'n> {
             self.normalize_notes,
             true,
         )?;
+        self.store_field_snapshot(&note)?;
         self.imports.log_updated(note, source_id);
         Ok(())
     }
 
+    /// Three-way merge an incoming note against the target note it shares a
+    /// GUID with, using the field snapshot recorded on a previous import as
+    /// the common ancestor.
+    ///
+    /// Falls through to [Self::imports]' conflicting bucket if the notetype's
+    /// field count has changed since the snapshot was taken, as there's no
+    /// sound way to align fields positionally in that case.
+    fn merge_note(
+        &mut self,
+        mut note: Note,
+        existing: NoteMeta,
+        ancestor: Vec<String>,
+    ) -> Result<()> {
+        let source_id = note.id;
+        note.id = existing.id;
+        self.munge_media(&mut note)?;
+        let target = self.get_expected_note(note.id)?;
+        if ancestor.len() != note.fields().len() || ancestor.len() != target.fields().len() {
+            let mut note = note;
+            note.id = source_id;
+            self.imports.log_conflicting(note);
+            return Ok(());
+        }
+        let merged_fields = merge_note_fields(&ancestor, target.fields(), note.fields());
+        note.fields_mut().clone_from_slice(&merged_fields);
+        let notetype = self.get_expected_notetype(note.notetype_id)?;
+        self.target_col.update_note_inner_without_cards(
+            &mut note,
+            &target,
+            &notetype,
+            self.usn,
+            true,
+            self.normalize_notes,
+            true,
+        )?;
+        self.store_field_snapshot(&note)?;
+        self.imports.log_merged(note, source_id);
+        Ok(())
+    }
+
+    /// Find the target note this "new" incoming note is really a rename of,
+    /// i.e. a target note with an identical content fingerprint. Returns
+    /// `None` if there's no such note, or more than one, as we can't safely
+    /// pick between several equally good candidates.
+    ///
+    /// Claims the target on a match, so that if a second incoming note in
+    /// the same import shares the same fingerprint (e.g. two genuine
+    /// duplicates, or the same note re-exported twice), it falls back to
+    /// being added as new instead of also matching and overwriting the
+    /// rename we're about to apply.
+    fn find_renamed_target(&mut self, note: &Note) -> Option<NoteId> {
+        let fingerprint = note_fingerprint(note);
+        let target_id = match self.target_fingerprints.get(&fingerprint) {
+            Some(ids) if ids.len() == 1 => Some(ids[0]),
+            _ => None,
+        };
+        if target_id.is_some() {
+            self.target_fingerprints.remove(&fingerprint);
+        }
+        target_id
+    }
+
+    /// Find a target note that's a likely (but not certain) duplicate of
+    /// `note`, if possible-duplicate detection was requested.
+    fn find_possible_duplicate(&self, note: &Note) -> Option<NoteId> {
+        self.possible_duplicates.as_ref()?.best_match(note)
+    }
+
+    /// Adopt `target_id` as this note's id and update it in place, rewriting
+    /// its GUID to the incoming one so future imports recognise the rename.
+    fn update_renamed_note(&mut self, mut note: Note, target_id: NoteId) -> Result<()> {
+        let source_id = note.id;
+        note.id = target_id;
+        self.munge_media(&mut note)?;
+        let original = self.get_expected_note(note.id)?;
+        let notetype = self.get_expected_notetype(note.notetype_id)?;
+        self.target_col.update_note_inner_without_cards(
+            &mut note,
+            &original,
+            &notetype,
+            self.usn,
+            true,
+            self.normalize_notes,
+            true,
+        )?;
+        self.store_field_snapshot(&note)?;
+        self.imports.log_renamed(note, source_id);
+        Ok(())
+    }
+
+    /// Record the fields of a just-imported note as the common ancestor for
+    /// the next time a note with this GUID is imported.
+    fn store_field_snapshot(&mut self, note: &Note) -> Result<()> {
+        self.target_col
+            .storage
+            .set_note_field_snapshot(&note.guid, note.fields())
+    }
+
     fn munge_media(&mut self, note: &mut Note) -> Result<()> {
         for field in note.fields_mut() {
             if let Some(new_field) = self.replace_media_refs(field) {
@@ -274,6 +555,267 @@ impl<'n> NoteContext<'n> {
     }
 }
 
+/// How to reorder an incoming note's field vector so it lines up with a
+/// notetype that was structurally merged into an existing one, rather than
+/// forked under a new id.
+struct FieldRemap {
+    /// `ordinals[incoming_ordinal]` is the corresponding ordinal in the
+    /// merged notetype.
+    ordinals: Vec<usize>,
+    /// The merged notetype's total field count.
+    final_len: usize,
+}
+
+impl FieldRemap {
+    fn apply(&self, note: &mut Note) {
+        let old_fields = mem::take(note.fields_mut());
+        let mut new_fields = vec![String::new(); self.final_len];
+        for (old_ord, value) in old_fields.into_iter().enumerate() {
+            if let Some(&new_ord) = self.ordinals.get(old_ord) {
+                new_fields[new_ord] = value;
+            }
+        }
+        *note.fields_mut() = new_fields;
+    }
+}
+
+struct StructuralAdditionPlan {
+    ordinals: Vec<usize>,
+    final_len: usize,
+}
+
+impl StructuralAdditionPlan {
+    fn into_remap(self) -> FieldRemap {
+        FieldRemap {
+            ordinals: self.ordinals,
+            final_len: self.final_len,
+        }
+    }
+}
+
+/// Check whether `incoming` only adds fields/templates on top of `existing`
+/// (no renames or removals), in which case we can overwrite `existing` in
+/// place instead of forking a new notetype id for it.
+///
+/// Existing fields keep their ordinal, so already-imported notes don't need
+/// reordering; new fields are appended at the end.
+fn structural_addition_plan(
+    incoming: &Notetype,
+    existing: &Notetype,
+) -> Option<StructuralAdditionPlan> {
+    let existing_field_names: Vec<&str> = existing.fields.iter().map(|f| f.name.as_str()).collect();
+    let incoming_field_names: Vec<&str> = incoming.fields.iter().map(|f| f.name.as_str()).collect();
+    let existing_template_names: HashSet<&str> =
+        existing.templates.iter().map(|t| t.name.as_str()).collect();
+    let incoming_template_names: HashSet<&str> =
+        incoming.templates.iter().map(|t| t.name.as_str()).collect();
+
+    let fields_are_pure_addition = existing_field_names
+        .iter()
+        .all(|name| incoming_field_names.contains(name));
+    let templates_are_pure_addition = existing_template_names
+        .iter()
+        .all(|name| incoming_template_names.contains(name));
+    if !fields_are_pure_addition || !templates_are_pure_addition {
+        return None;
+    }
+
+    let mut final_field_names = existing_field_names;
+    for name in &incoming_field_names {
+        if !final_field_names.contains(name) {
+            final_field_names.push(name);
+        }
+    }
+    let ordinals = incoming_field_names
+        .iter()
+        .map(|name| final_field_names.iter().position(|n| n == name).unwrap())
+        .collect();
+
+    Some(StructuralAdditionPlan {
+        ordinals,
+        final_len: final_field_names.len(),
+    })
+}
+
+/// Merge `target` and `incoming` field-by-field, using `ancestor` (the fields
+/// as they stood at the last import of this GUID) to tell which side
+/// actually changed.
+///
+/// - Changed only on one side: take the side that changed.
+/// - Changed identically on both sides: take either.
+/// - Changed differently on both sides: keep the target value, but wrap both
+///   versions in an HTML conflict marker so neither is silently lost.
+///
+/// All three slices are expected to be the same length; callers are
+/// responsible for falling back to the conflict path when arity differs.
+fn merge_note_fields(ancestor: &[String], target: &[String], incoming: &[String]) -> Vec<String> {
+    ancestor
+        .iter()
+        .zip(target)
+        .zip(incoming)
+        .map(|((a, t), s)| {
+            if s == a {
+                t.clone()
+            } else if t == a || t == s {
+                // Only the incoming side changed, or both sides changed the same way.
+                s.clone()
+            } else {
+                format!("<!-- conflict --><span>{t}</span><span>{s}</span>")
+            }
+        })
+        .collect()
+}
+
+/// Fingerprint a note's content so that two notes with different GUIDs but
+/// the same notetype and field contents can be recognised as the same note
+/// (e.g. after being re-exported, which regenerates GUIDs). Media references
+/// are stripped before hashing, as the same image/audio file is often
+/// re-packaged under a different name.
+fn note_fingerprint(note: &Note) -> Sha1Hash {
+    let mut hasher = Sha1::new();
+    hasher.update(note.notetype_id.0.to_le_bytes());
+    for field in note.fields() {
+        hasher.update(strip_media_refs(field).as_bytes());
+        // Separator so e.g. ["ab", "c"] and ["a", "bc"] don't collide.
+        hasher.update([0]);
+    }
+    hasher.finalize().into()
+}
+
+/// Blank out the file names of all media references in a field. The
+/// surrounding markup and text are left in place.
+fn strip_media_refs(field: &str) -> Cow<str> {
+    match replace_media_refs(field, |_name| Some(String::new())) {
+        Some(stripped) => Cow::Owned(stripped),
+        None => Cow::Borrowed(field),
+    }
+}
+
+/// Lazily-built inverted term index over target notes, used to surface
+/// incoming notes that look like duplicates of an existing note under a
+/// different GUID. Unlike [note_fingerprint], this tolerates typos and minor
+/// edits rather than requiring an exact match.
+struct PossibleDuplicateIndex {
+    /// Minimum combined score (0.0-1.0) for a candidate to be surfaced.
+    threshold: f32,
+    token_index: HashMap<String, Vec<NoteId>>,
+    candidates: HashMap<NoteId, CandidateFingerprint>,
+}
+
+struct CandidateFingerprint {
+    tokens: HashSet<String>,
+    first_field: String,
+}
+
+impl PossibleDuplicateIndex {
+    fn build(notes: &[Note], threshold: f32) -> Self {
+        let mut token_index: HashMap<String, Vec<NoteId>> = HashMap::new();
+        let mut candidates = HashMap::new();
+        for note in notes {
+            let tokens = note_tokens(note);
+            for token in &tokens {
+                token_index.entry(token.clone()).or_default().push(note.id);
+            }
+            let first_field = note.fields().first().cloned().unwrap_or_default();
+            candidates.insert(
+                note.id,
+                CandidateFingerprint {
+                    tokens,
+                    first_field,
+                },
+            );
+        }
+        Self {
+            threshold,
+            token_index,
+            candidates,
+        }
+    }
+
+    /// The highest-scoring candidate sharing at least one token with `note`,
+    /// if its score crosses [Self::threshold].
+    fn best_match(&self, note: &Note) -> Option<NoteId> {
+        let tokens = note_tokens(note);
+        let first_field = note
+            .fields()
+            .first()
+            .map(String::as_str)
+            .unwrap_or_default();
+        let mut seen = HashSet::new();
+        let mut best: Option<(NoteId, f32)> = None;
+        for token in &tokens {
+            let Some(candidate_ids) = self.token_index.get(token) else {
+                continue;
+            };
+            for &candidate_id in candidate_ids {
+                if !seen.insert(candidate_id) {
+                    continue;
+                }
+                let candidate = &self.candidates[&candidate_id];
+                let score = (jaccard_similarity(&tokens, &candidate.tokens)
+                    + normalized_levenshtein_similarity(first_field, &candidate.first_field))
+                    / 2.0;
+                if score >= self.threshold
+                    && best.map_or(true, |(_, best_score)| score > best_score)
+                {
+                    best = Some((candidate_id, score));
+                }
+            }
+        }
+        best.map(|(id, _)| id)
+    }
+}
+
+/// The lowercased, alphanumeric-only term set of a note's fields.
+fn note_tokens(note: &Note) -> HashSet<String> {
+    note.fields()
+        .iter()
+        .flat_map(|field| {
+            field
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|term| !term.is_empty())
+                .map(|term| term.to_lowercase())
+        })
+        .collect()
+}
+
+fn jaccard_similarity(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let intersection = a.intersection(b).count();
+    let union = a.union(b).count();
+    intersection as f32 / union as f32
+}
+
+fn normalized_levenshtein_similarity(a: &str, b: &str) -> f32 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let max_len = a.len().max(b.len());
+    if max_len == 0 {
+        return 1.0;
+    }
+    1.0 - (levenshtein_distance(&a, &b) as f32 / max_len as f32)
+}
+
+fn levenshtein_distance(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, &a_char) in a.iter().enumerate() {
+        let mut prev_diag = row[0];
+        row[0] = i + 1;
+        for (j, &b_char) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if a_char == b_char {
+                prev_diag
+            } else {
+                1 + row[j + 1].min(row[j]).min(prev_diag)
+            };
+            prev_diag = above;
+        }
+    }
+    row[b.len()]
+}
+
 impl Notetype {
     fn schema_hash(&self) -> Sha1Hash {
         let mut hasher = Sha1::new();
@@ -298,14 +840,50 @@ mod test {
         ($col:expr, $note:expr, $old_notetype:expr => $new_notetype:expr) => {{
             let mut media_map = MediaUseMap::default();
             let mut progress = $col.new_progress_handler();
-            let mut ctx = NoteContext::new(Usn(1), &mut $col, &mut media_map).unwrap();
+            let mut ctx = NoteContext::new(
+                Usn(1),
+                &mut $col,
+                &mut media_map,
+                ImportConflictResolution::default(),
+                None,
+            )
+            .unwrap();
             ctx.remapped_notetypes.insert($old_notetype, $new_notetype);
             ctx.import_notes(vec![$note], &mut progress).unwrap();
             ctx.imports.log
         }};
+        ($col:expr, $note:expr, resolution: $resolution:expr) => {{
+            let mut media_map = MediaUseMap::default();
+            let mut progress = $col.new_progress_handler();
+            let mut ctx =
+                NoteContext::new(Usn(1), &mut $col, &mut media_map, $resolution, None).unwrap();
+            ctx.import_notes(vec![$note], &mut progress).unwrap();
+            ctx.imports.log
+        }};
+        ($col:expr, $note:expr, possible_duplicate_threshold: $threshold:expr) => {{
+            let mut media_map = MediaUseMap::default();
+            let mut progress = $col.new_progress_handler();
+            let mut ctx = NoteContext::new(
+                Usn(1),
+                &mut $col,
+                &mut media_map,
+                ImportConflictResolution::default(),
+                Some($threshold),
+            )
+            .unwrap();
+            ctx.import_notes(vec![$note], &mut progress).unwrap();
+            ctx.imports.log
+        }};
         ($col:expr, $note:expr, $media_map:expr) => {{
             let mut progress = $col.new_progress_handler();
-            let mut ctx = NoteContext::new(Usn(1), &mut $col, &mut $media_map).unwrap();
+            let mut ctx = NoteContext::new(
+                Usn(1),
+                &mut $col,
+                &mut $media_map,
+                ImportConflictResolution::default(),
+                None,
+            )
+            .unwrap();
             ctx.import_notes(vec![$note], &mut progress).unwrap();
             ctx.imports.log
         }};
@@ -322,6 +900,8 @@ mod test {
             assert_eq!($log.$state.pop().unwrap().fields, $fields);
             assert!($log.new.is_empty());
             assert!($log.updated.is_empty());
+            assert!($log.merged.is_empty());
+            assert!($log.renamed.is_empty());
             assert!($log.duplicate.is_empty());
             assert!($log.conflicting.is_empty());
         };
@@ -334,6 +914,21 @@ mod test {
                 .query_row("SELECT id FROM notes WHERE guid = ?", [guid], |r| r.get(0))
                 .unwrap()
         }
+
+        /// Pretend a note with this GUID was already imported once before,
+        /// so the next import of the same GUID can three-way merge against
+        /// `fields` as the common ancestor.
+        fn set_note_field_snapshot(&self, guid: &str, fields: &[String]) {
+            self.storage.set_note_field_snapshot(guid, fields).unwrap()
+        }
+
+        /// Directly diverge the target's copy of a note's fields, simulating
+        /// local edits made since the last import.
+        fn set_note_fields(&self, id: NoteId, fields: &[String]) {
+            let mut note = self.storage.get_note(id).unwrap().unwrap();
+            note.fields_mut().clone_from_slice(fields);
+            self.storage.update_note(&note).unwrap();
+        }
     }
 
     #[test]
@@ -373,6 +968,222 @@ mod test {
         assert_note_logged!(log, updated, &["updated", ""]);
     }
 
+    #[test]
+    fn should_three_way_merge_note_if_ancestor_snapshot_exists() {
+        let mut col = Collection::new();
+        let note = NoteAdder::basic(&mut col).add(&mut col);
+        col.set_note_field_snapshot(&note.guid, note.fields());
+
+        // the target only changed field 0 since the snapshot was taken
+        col.set_note_fields(note.id, &["target change".to_string(), "".to_string()]);
+
+        // the incoming note only changed field 1
+        let mut incoming = note;
+        incoming.id.0 = 42;
+        incoming.mtime.0 += 1;
+        incoming.fields_mut()[1] = "incoming change".to_string();
+
+        let mut log = import_note!(col, incoming, resolution: ImportConflictResolution::Merge);
+        assert_note_logged!(log, merged, &["target change", "incoming change"]);
+    }
+
+    #[test]
+    fn should_wrap_diverging_fields_in_conflict_marker_on_merge() {
+        let mut col = Collection::new();
+        let note = NoteAdder::basic(&mut col).add(&mut col);
+        col.set_note_field_snapshot(&note.guid, note.fields());
+
+        // both sides change field 0, but to different values
+        col.set_note_fields(note.id, &["target".to_string(), "".to_string()]);
+        let mut incoming = note;
+        incoming.id.0 = 42;
+        incoming.mtime.0 += 1;
+        incoming.fields_mut()[0] = "incoming".to_string();
+
+        let mut log = import_note!(col, incoming, resolution: ImportConflictResolution::Merge);
+        assert_note_logged!(
+            log,
+            merged,
+            &[
+                "<!-- conflict --><span>target</span><span>incoming</span>",
+                ""
+            ]
+        );
+    }
+
+    #[test]
+    fn should_flag_conflicting_note_on_merge_without_ancestor_if_fields_diverge() {
+        let mut col = Collection::new();
+        let mut note = NoteAdder::basic(&mut col).add(&mut col);
+        note.fields_mut()[0] = "target".to_string();
+        col.storage.update_note(&note).unwrap();
+
+        // no snapshot has ever been recorded for this GUID, and the incoming note's
+        // fields genuinely disagree with the target's, so there's no sound way to
+        // pick a winner
+        let mut incoming = note.clone();
+        incoming.id.0 = 42;
+        incoming.fields_mut()[0] = "incoming".to_string();
+
+        let mut log = import_note!(col, incoming, resolution: ImportConflictResolution::Merge);
+        assert_eq!(col.get_all_notes()[0].fields()[0], "target");
+        assert_note_logged!(log, conflicting, &["incoming", ""]);
+    }
+
+    #[test]
+    fn should_treat_note_as_duplicate_on_merge_without_ancestor_if_fields_match() {
+        let mut col = Collection::new();
+        let mut note = NoteAdder::basic(&mut col).add(&mut col);
+        note.fields_mut()[0] = "unchanged".to_string();
+        col.storage.update_note(&note).unwrap();
+
+        // no snapshot recorded, but both sides already agree, so it's safe to treat
+        // this as a duplicate rather than flagging a conflict
+        let mut incoming = note.clone();
+        incoming.id.0 = 42;
+
+        let mut log = import_note!(col, incoming, resolution: ImportConflictResolution::Merge);
+        assert_note_logged!(log, duplicate, &["unchanged", ""]);
+    }
+
+    #[test]
+    fn should_always_take_incoming_note_with_prefer_source_resolution() {
+        let mut col = Collection::new();
+        let mut note = NoteAdder::basic(&mut col).add(&mut col);
+        // older mtime would be a duplicate under the default PreferNewest resolution
+        note.mtime.0 -= 1;
+        note.fields_mut()[0] = "incoming".to_string();
+
+        let mut log = import_note!(col, note, resolution: ImportConflictResolution::PreferSource);
+        assert_eq!(col.get_all_notes()[0].fields()[0], "incoming");
+        assert_note_logged!(log, updated, &["incoming", ""]);
+    }
+
+    #[test]
+    fn should_always_keep_target_note_with_prefer_target_resolution() {
+        let mut col = Collection::new();
+        let mut note = NoteAdder::basic(&mut col).add(&mut col);
+        // newer mtime would be taken under the default PreferNewest resolution
+        note.mtime.0 += 1;
+        note.fields_mut()[0] = "incoming".to_string();
+
+        let mut log = import_note!(col, note, resolution: ImportConflictResolution::PreferTarget);
+        assert_eq!(col.get_all_notes()[0].fields()[0], "");
+        assert_note_logged!(log, duplicate, &["incoming", ""]);
+    }
+
+    #[test]
+    fn should_update_note_with_matching_fingerprint_instead_of_adding_duplicate() {
+        let mut col = Collection::new();
+        let mut note = NoteAdder::basic(&mut col).add(&mut col);
+        note.fields_mut()[0] = "unchanged content".to_string();
+        col.storage.update_note(&note).unwrap();
+        let original_id = note.id;
+
+        // a brand-new GUID, as if the note had been re-exported from elsewhere
+        note.guid = "regenerated".to_string();
+        note.id.0 = 42;
+
+        let mut log = import_note!(col, note);
+        assert_eq!(col.get_all_notes().len(), 1);
+        assert_eq!(col.note_id_for_guid("regenerated"), original_id);
+        assert_note_logged!(log, renamed, &["unchanged content", ""]);
+    }
+
+    #[test]
+    fn should_add_note_as_new_if_fingerprint_matches_more_than_one_target() {
+        let mut col = Collection::new();
+        let mut first = NoteAdder::basic(&mut col).add(&mut col);
+        first.guid = "first".to_string();
+        col.storage.update_note(&first).unwrap();
+        let mut second = NoteAdder::basic(&mut col).add(&mut col);
+        second.guid = "second".to_string();
+        col.storage.update_note(&second).unwrap();
+
+        let mut incoming = first;
+        incoming.guid = "regenerated".to_string();
+        incoming.id.0 = 42;
+
+        let mut log = import_note!(col, incoming);
+        assert_eq!(col.get_all_notes().len(), 3);
+        assert_note_logged!(log, new, &["", ""]);
+    }
+
+    #[test]
+    fn should_add_second_note_as_new_if_fingerprint_already_claimed_by_earlier_rename_in_same_import() {
+        let mut col = Collection::new();
+        let target = NoteAdder::basic(&mut col).add(&mut col);
+        let target_id = target.id;
+
+        // two incoming notes that both regenerated their GUID but share the same
+        // content fingerprint as the one target note
+        let mut first = target.clone();
+        first.guid = "first".to_string();
+        first.id.0 = 42;
+        let mut second = target;
+        second.guid = "second".to_string();
+        second.id.0 = 43;
+
+        let mut media_map = MediaUseMap::default();
+        let mut progress = col.new_progress_handler();
+        let mut ctx = NoteContext::new(
+            Usn(1),
+            &mut col,
+            &mut media_map,
+            ImportConflictResolution::default(),
+            None,
+        )
+        .unwrap();
+        ctx.import_notes(vec![first, second], &mut progress).unwrap();
+        let log = ctx.imports.log;
+
+        // only the first claims the rename; the second can't safely be assumed to be
+        // the same note a second time, so it's added as new instead of clobbering it
+        assert_eq!(col.get_all_notes().len(), 2);
+        assert_eq!(log.renamed.len(), 1);
+        assert_eq!(log.new.len(), 1);
+        assert_eq!(col.note_id_for_guid("first"), target_id);
+        assert_ne!(col.note_id_for_guid("second"), target_id);
+    }
+
+    #[test]
+    fn should_flag_possible_duplicate_without_suppressing_import() {
+        let mut col = Collection::new();
+        let mut existing = NoteAdder::basic(&mut col).add(&mut col);
+        existing.fields_mut()[0] = "the quick brown fox".to_string();
+        col.storage.update_note(&existing).unwrap();
+
+        let mut incoming = existing.clone();
+        incoming.guid = "other".to_string();
+        incoming.id.0 = 42;
+        // a typo away from the existing note's first field; the combined score is
+        // (Jaccard 3/5 + Levenshtein 1 - 2/19) / 2, about 0.75
+        incoming.fields_mut()[0] = "the quikc brown fox".to_string();
+
+        let mut log = import_note!(col, incoming, possible_duplicate_threshold: 0.7);
+        assert_eq!(col.get_all_notes().len(), 2);
+        assert_eq!(log.possible_duplicates.len(), 1);
+        assert_eq!(log.possible_duplicates[0].target_id, existing.id);
+        assert_note_logged!(log, new, &["the quikc brown fox", ""]);
+    }
+
+    #[test]
+    fn should_not_flag_possible_duplicate_below_threshold() {
+        let mut col = Collection::new();
+        let mut existing = NoteAdder::basic(&mut col).add(&mut col);
+        existing.fields_mut()[0] = "the quick brown fox".to_string();
+        col.storage.update_note(&existing).unwrap();
+
+        let mut incoming = existing.clone();
+        incoming.guid = "other".to_string();
+        incoming.id.0 = 42;
+        incoming.fields_mut()[0] = "a completely unrelated sentence".to_string();
+
+        let mut log = import_note!(col, incoming, possible_duplicate_threshold: 0.85);
+        assert!(log.possible_duplicates.is_empty());
+        assert_note_logged!(log, new, &["a completely unrelated sentence", ""]);
+    }
+
     #[test]
     fn should_ignore_note_if_guid_already_exists_with_different_notetype() {
         let mut col = Collection::new();
@@ -386,6 +1197,21 @@ mod test {
         assert_note_logged!(log, conflicting, &["updated", ""]);
     }
 
+    #[test]
+    fn should_flag_conflicting_note_if_guid_already_exists_with_different_notetype_and_target_is_newer() {
+        let mut col = Collection::new();
+        let mut note = NoteAdder::basic(&mut col).add(&mut col);
+        note.notetype_id.0 = 42;
+        // target is newer, which would make this a silently-dropped duplicate if the
+        // notetype matched, but notetype mismatches are always conflicting
+        note.mtime.0 -= 1;
+        note.fields_mut()[0] = "updated".to_string();
+
+        let mut log = import_note!(col, note);
+        assert_eq!(col.get_all_notes()[0].fields()[0], "");
+        assert_note_logged!(log, conflicting, &["updated", ""]);
+    }
+
     #[test]
     fn should_add_note_with_remapped_notetype_if_in_notetype_map() {
         let mut col = Collection::new();
@@ -426,4 +1252,87 @@ mod test {
         assert_eq!(col.get_all_notes()[0].fields()[0], "<img src='bar.jpg'>");
         assert_note_logged!(log, new, &[" bar.jpg ", ""]);
     }
+
+    #[test]
+    fn should_reorder_note_fields_according_to_structural_merge_remap() {
+        let mut col = Collection::new();
+        let mut note = NoteAdder::basic(&mut col).note();
+        note.fields_mut()[0] = "front".to_string();
+        note.fields_mut()[1] = "back".to_string();
+
+        // e.g. an "Extra" field was inserted between Front and Back upstream
+        let remap = FieldRemap {
+            ordinals: vec![0, 2],
+            final_len: 3,
+        };
+        remap.apply(&mut note);
+
+        assert_eq!(note.fields()[0], "front");
+        assert_eq!(note.fields()[1], "");
+        assert_eq!(note.fields()[2], "back");
+    }
+
+    #[test]
+    fn should_migrate_existing_notes_when_structurally_merging_notetype_fields() {
+        let mut col = Collection::new();
+        let note = NoteAdder::basic(&mut col).add(&mut col);
+        let basic_ntid = note.notetype_id;
+        let existing_notetype = col.storage.get_notetype(basic_ntid).unwrap().unwrap();
+
+        let mut incoming = existing_notetype.clone();
+        incoming.mtime_secs.0 += 1;
+        let mut extra_field = existing_notetype.fields[0].clone();
+        extra_field.name = "Extra".to_string();
+        incoming.fields.push(extra_field);
+
+        let mut media_map = MediaUseMap::default();
+        let mut ctx = NoteContext::new(
+            Usn(1),
+            &mut col,
+            &mut media_map,
+            ImportConflictResolution::default(),
+            None,
+        )
+        .unwrap();
+        ctx.import_notetypes(vec![incoming]).unwrap();
+
+        // the pre-existing note keeps its id and content, resized to the merged
+        // notetype's new field count rather than left mismatched with it
+        let migrated = col.storage.get_note(note.id).unwrap().unwrap();
+        assert_eq!(migrated.fields().len(), 3);
+        assert_eq!(&migrated.fields()[..2], note.fields());
+        assert_eq!(migrated.fields()[2], "");
+    }
+
+    #[test]
+    fn should_remap_notetype_id_instead_of_merging_when_existing_field_is_renamed() {
+        let mut col = Collection::new();
+        let note = NoteAdder::basic(&mut col).add(&mut col);
+        let basic_ntid = note.notetype_id;
+        let existing_notetype = col.storage.get_notetype(basic_ntid).unwrap().unwrap();
+
+        // not a pure addition: an existing field is renamed rather than kept
+        let mut incoming = existing_notetype.clone();
+        incoming.mtime_secs.0 += 1;
+        incoming.fields[0].name = "Renamed".to_string();
+
+        let mut media_map = MediaUseMap::default();
+        let mut ctx = NoteContext::new(
+            Usn(1),
+            &mut col,
+            &mut media_map,
+            ImportConflictResolution::default(),
+            None,
+        )
+        .unwrap();
+        ctx.import_notetypes(vec![incoming]).unwrap();
+
+        let remapped_id = *ctx.remapped_notetypes.get(&basic_ntid).unwrap();
+        assert_ne!(remapped_id, basic_ntid);
+        assert!(ctx.field_remaps.get(&basic_ntid).is_none());
+        // the pre-existing note and notetype are left untouched
+        let existing_note = col.storage.get_note(note.id).unwrap().unwrap();
+        assert_eq!(existing_note.notetype_id, basic_ntid);
+        assert_eq!(existing_note.fields(), note.fields());
+    }
 }
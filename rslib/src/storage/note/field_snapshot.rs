@@ -0,0 +1,47 @@
+// Copyright: Ankitects Pty Ltd and contributors
+// License: GNU AGPL, version 3 or later; http://www.gnu.org/licenses/agpl.html
+
+//! A side table recording the fields a note had immediately after its last
+//! import, kept so a later import of the same GUID can three-way merge
+//! against it instead of blindly preferring one side. Separate from the
+//! `notes` table itself, as most collections will never use
+//! [ImportConflictResolution::Merge](crate::import_export::package::apkg::import::notes::ImportConflictResolution::Merge)
+//! and the snapshot has no bearing on sync or card scheduling.
+//!
+//! The table itself is created by the schema upgrade in
+//! `storage::upgrades::note_field_snapshots`, not here; by the time a
+//! collection is open, it's guaranteed to already exist.
+
+use rusqlite::params;
+use rusqlite::OptionalExtension;
+
+use crate::prelude::*;
+use crate::text::join_fields;
+use crate::text::split_fields;
+
+impl super::super::SqliteStorage {
+    /// The fields of the note with the given GUID as they stood after its
+    /// last import, or `None` if this GUID has never been imported before.
+    pub(crate) fn get_note_field_snapshot(&self, guid: &str) -> Result<Option<Vec<String>>> {
+        Ok(self
+            .db
+            .query_row(
+                "SELECT fields FROM note_field_snapshots WHERE guid = ?",
+                params![guid],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?
+            .map(|fields| split_fields(&fields)))
+    }
+
+    /// Record `fields` as the common ancestor for the next import of a note
+    /// with this GUID, overwriting any previous snapshot.
+    pub(crate) fn set_note_field_snapshot(&self, guid: &str, fields: &[String]) -> Result<()> {
+        self.db.execute(
+            "INSERT INTO note_field_snapshots (guid, fields) VALUES (?, ?)
+             ON CONFLICT (guid) DO UPDATE SET fields = excluded.fields",
+            params![guid, join_fields(fields)],
+        )?;
+        Ok(())
+    }
+}